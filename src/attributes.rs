@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use logos::{Lexer, Logos};
+
+use crate::{
+	bail,
+	error::{Context as _, Result},
+};
+
+/// A parsed attribute list, keyed by the attribute name exactly as it appears
+/// in the playlist (e.g. `BANDWIDTH`, `RESOLUTION`). Unknown keys are kept
+/// verbatim so nothing is lost when a playlist carries attributes this crate
+/// does not model yet.
+pub type Attributes<'a> = HashMap<&'a str, AttrValue<'a>>;
+
+/// The value half of an `attribute-list` pair as defined by the HLS grammar.
+///
+/// Attribute lists mix a handful of syntactic value kinds, and the reader keeps
+/// them apart so struct construction can ask for exactly the shape it expects
+/// and reject anything else with a clear error.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue<'a> {
+	/// A quoted string, with the surrounding double quotes stripped.
+	Quoted(&'a str),
+	/// A decimal integer.
+	Integer(usize),
+	/// A floating-point number.
+	Float(f64),
+	/// A `WIDTHxHEIGHT` decimal resolution.
+	Resolution(usize, usize),
+	/// An unquoted enumerated keyword (e.g. `AES-128`, `SDR`).
+	Keyword(&'a str),
+}
+
+impl<'a> AttrValue<'a> {
+	/// Returns the contents of a quoted-string attribute.
+	pub fn quoted(&self) -> Result<&'a str> {
+		match self {
+			AttrValue::Quoted(value) => Ok(value),
+			other => bail!("expected a quoted string, found {other:?}"),
+		}
+	}
+
+	/// Returns the value of an integer attribute.
+	pub fn integer(&self) -> Result<usize> {
+		match self {
+			AttrValue::Integer(value) => Ok(*value),
+			other => bail!("expected an integer, found {other:?}"),
+		}
+	}
+
+	/// Returns the value of a floating-point attribute.
+	pub fn float(&self) -> Result<f64> {
+		match self {
+			AttrValue::Float(value) => Ok(*value),
+			other => bail!("expected a float, found {other:?}"),
+		}
+	}
+
+	/// Returns the `(width, height)` of a resolution attribute.
+	pub fn resolution(&self) -> Result<(usize, usize)> {
+		match self {
+			AttrValue::Resolution(width, height) => Ok((*width, *height)),
+			other => bail!("expected a resolution, found {other:?}"),
+		}
+	}
+
+	/// Returns the text of an enumerated-keyword attribute.
+	pub fn keyword(&self) -> Result<&'a str> {
+		match self {
+			AttrValue::Keyword(value) => Ok(value),
+			other => bail!("expected a keyword, found {other:?}"),
+		}
+	}
+
+	/// Returns the text of an attribute that may be written either quoted or as
+	/// a bare keyword (e.g. `CLOSED-CAPTIONS="cc1"` vs `CLOSED-CAPTIONS=NONE`).
+	pub fn text(&self) -> Result<&'a str> {
+		match self {
+			AttrValue::Quoted(value) | AttrValue::Keyword(value) => Ok(value),
+			other => bail!("expected a string, found {other:?}"),
+		}
+	}
+}
+
+/// Classifies the tokens a generic [`read_attributes`] scan walks over.
+///
+/// Each playlist parser has its own [`Logos`] token type, so the reader is
+/// written against this trait rather than a concrete enum. A token is either an
+/// attribute name, an attribute value, one of the structural separators, or a
+/// marker that the logical line has ended (a URI or the next tag).
+pub trait AttributeToken<'a> {
+	/// The attribute name this token introduces, if any.
+	fn attr_name(&self) -> Option<&'a str>;
+	/// The attribute value this token carries, if any.
+	fn attr_value(&self) -> Option<AttrValue<'a>>;
+	/// Whether this token is the `=` that joins a name to its value.
+	fn is_equal(&self) -> bool;
+	/// Whether this token separates attributes (`,`) or the tag from its list (`:`).
+	fn is_separator(&self) -> bool;
+	/// Whether this token terminates the logical line (a URI or a new tag).
+	fn ends_line(&self) -> bool;
+}
+
+/// Reads a comma-separated `attribute-list` from `lexer`, starting at the token
+/// after a tag such as `#EXT-X-STREAM-INF:`, and collects it into a map keyed
+/// by attribute name.
+///
+/// The scan stops — without consuming it — at the first token that ends the
+/// logical line (a `UriValue` or the next tag), so the caller can read the
+/// trailing URI or continue its own loop. Commas act as separators only between
+/// complete pairs; quoted strings are a single token and so naturally swallow
+/// any commas inside them.
+pub fn read_attributes<'a, T>(lexer: &mut Lexer<'a, T>) -> Result<Attributes<'a>>
+where
+	T: Logos<'a, Source = str, Error = String> + AttributeToken<'a> + Clone,
+	T::Extras: Clone,
+{
+	let mut attributes = Attributes::new();
+	let mut name: Option<&'a str> = None;
+	let mut expect_value = false;
+
+	loop {
+		// Peek ahead so the line terminator is left for the caller to handle.
+		let mut ahead = lexer.clone();
+		match ahead.next() {
+			None => break,
+			Some(Ok(token)) if token.ends_line() => break,
+			Some(Err(err)) => return Err(err.into()),
+			Some(Ok(_)) => {}
+		}
+
+		let token = lexer.next().context("unexpected end of attribute list")??;
+		if token.is_equal() {
+			expect_value = true;
+		} else if token.is_separator() {
+			expect_value = false;
+		} else if expect_value {
+			let key = name.take().context("attribute value without a name")?;
+			let value = token.attr_value().context("invalid attribute value")?;
+			attributes.insert(key, value);
+			expect_value = false;
+		} else {
+			name = Some(token.attr_name().context("expected an attribute name")?);
+		}
+	}
+
+	Ok(attributes)
+}
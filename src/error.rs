@@ -1,9 +1,17 @@
+use std::ops::Range;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
 	#[error("Generic Error: {0}")]
 	Generic(String),
+	#[error("parse error at line {line} (bytes {span:?}): {message}")]
+	Parse {
+		message: String,
+		span: Range<usize>,
+		line: usize,
+	},
 }
 
 impl From<String> for Error {
@@ -30,6 +38,20 @@ impl<T> Context<T> for Option<T> {
 #[macro_export]
 macro_rules! bail {
 	($($arg:tt)*) => {
-		return Err(Error::Generic(format!($($arg)*)))
+		return Err($crate::error::Error::Generic(format!($($arg)*)))
+	};
+}
+
+/// Bails with a [`Error::Parse`] carrying the current token's byte span and
+/// line number, taken from `$lexer` (its [`logos::Lexer::span`] and the line
+/// counter kept in its extras).
+#[macro_export]
+macro_rules! bail_at {
+	($lexer:expr, $($arg:tt)*) => {
+		return Err($crate::error::Error::Parse {
+			message: format!($($arg)*),
+			span: $lexer.span(),
+			line: $lexer.extras + 1,
+		})
 	};
 }
@@ -0,0 +1,38 @@
+pub mod attributes;
+pub mod error;
+pub mod media_playlist;
+pub mod multi_variant;
+
+use error::Result;
+use media_playlist::MediaPlaylist;
+use multi_variant::MultiVariantPlaylist;
+
+/// A parsed m3u8 playlist, either a master (multivariant) playlist or a media
+/// playlist. Use [`parse_playlist`] when the kind is not known in advance.
+#[derive(Debug, PartialEq)]
+pub enum Playlist {
+	/// A multivariant playlist describing variant and I-frame streams.
+	MasterPlaylist(MultiVariantPlaylist),
+	/// A media playlist describing the segments of a single stream.
+	MediaPlaylist(MediaPlaylist),
+}
+
+/// Parses a playlist without the caller having to know its kind ahead of time.
+///
+/// Classification follows the HLS rule: a playlist carrying any
+/// `#EXT-X-STREAM-INF`/`#EXT-X-I-FRAME-STREAM-INF` line is a master playlist,
+/// while one carrying `#EXTINF` segment lines is a media playlist. The input is
+/// scanned once to classify it before dispatching to the matching parser.
+pub fn parse_playlist(input: &str) -> Result<Playlist> {
+	for line in input.lines() {
+		let line = line.trim_start();
+		if line.starts_with("#EXT-X-STREAM-INF") || line.starts_with("#EXT-X-I-FRAME-STREAM-INF") {
+			return Ok(Playlist::MasterPlaylist(multi_variant::parse(input)?));
+		}
+		if line.starts_with("#EXTINF") {
+			return Ok(Playlist::MediaPlaylist(media_playlist::parse(input)?));
+		}
+	}
+
+	bail!("input is neither a master nor a media playlist")
+}
@@ -1,15 +1,24 @@
-use std::ops::Range;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
 
 use crate::{
-	bail,
-	error::{Context as _, Error, Result},
+	attributes::{read_attributes, AttrValue, AttributeToken},
+	bail, bail_at,
+	error::{Context as _, Result},
 };
 use logos::Logos;
 
-#[derive(Logos, Debug, PartialEq)]
-#[logos(skip r"[ \t\n\f]+")]
+#[derive(Logos, Debug, Clone, PartialEq)]
+#[logos(extras = usize)]
 #[logos(error = String)]
 pub enum Token<'a> {
+	#[regex(r"[ \t\n\f]+", |lex| {
+		lex.extras += lex.slice().bytes().filter(|&b| b == b'\n').count();
+		logos::Skip
+	})]
+	Whitespace,
+
 	#[token("#EXTM3U")]
 	ExtM3U,
 	#[token("#EXT-X-ENDLIST")]
@@ -35,17 +44,14 @@ pub enum Token<'a> {
 	#[token("#EXT-X-BYTERANGE")]
 	ByteRange,
 
-	#[token("METHOD")]
-	Method,
-	#[token("URI")]
-	Uri,
-
 	#[token("=")]
 	Equal,
 	#[token(",")]
 	Comma,
 	#[token(":")]
 	Colon,
+	#[token("@")]
+	At,
 
 	#[regex(r"[0-9]+\.[0-9]+", |lex| lexical::parse(lex.slice()).ok())]
 	Float(f64),
@@ -54,26 +60,12 @@ pub enum Token<'a> {
 	#[regex(r#""([^"]*)""#, |lex| lex.slice()[1..lex.slice().len() - 1].as_ref())]
 	String(&'a str),
 
-	#[regex(r"AES-128|SAMPLE-AES|NONE", |lex| match lex.slice() {
-		"AES-128" => Method::Aes128,
-		"SAMPLE-AES" => Method::SampleAes,
-		"NONE" => Method::None,
-		_ => unreachable!(),
-	})]
-	MethodValue(Method),
 	#[regex(r"YES|NO", |lex| match lex.slice() {
 		"YES" => true,
 		"NO" => false,
 		_ => unreachable!(),
 	})]
 	AllowCacheValue(bool),
-	#[regex(r"[0-9]+@[0-9]+", |lex| {
-		let mut parts = lex.slice().split('@');
-		let length: usize = lexical::parse(parts.next().unwrap()).unwrap();
-		let offset: usize = lexical::parse(parts.next().unwrap()).unwrap();
-		length..offset
-	})]
-	ByteRangeValue(Range<usize>),
 	#[regex(r"VOD|EVENT", |lex| match lex.slice() {
 		"VOD" => PlaylistType::Vod,
 		"EVENT" => PlaylistType::Event,
@@ -82,6 +74,55 @@ pub enum Token<'a> {
 	PlaylistTypeValue(PlaylistType),
 	#[regex(r"https?://[^ \t\n\f]+", |lex| lex.slice())]
 	UriValue(&'a str),
+
+	/// An attribute name (or unquoted enumerated keyword) in an attribute list.
+	#[regex(r"[A-Z][A-Z0-9\-]*", |lex| lex.slice(), priority = 1)]
+	AttrName(&'a str),
+}
+
+impl<'a> AttributeToken<'a> for Token<'a> {
+	fn attr_name(&self) -> Option<&'a str> {
+		match self {
+			Token::AttrName(name) => Some(name),
+			_ => None,
+		}
+	}
+
+	fn attr_value(&self) -> Option<AttrValue<'a>> {
+		match self {
+			Token::String(value) => Some(AttrValue::Quoted(value)),
+			Token::Integer(value) => Some(AttrValue::Integer(*value)),
+			Token::Float(value) => Some(AttrValue::Float(*value)),
+			Token::AttrName(value) => Some(AttrValue::Keyword(value)),
+			_ => None,
+		}
+	}
+
+	fn is_equal(&self) -> bool {
+		matches!(self, Token::Equal)
+	}
+
+	fn is_separator(&self) -> bool {
+		matches!(self, Token::Comma | Token::Colon)
+	}
+
+	fn ends_line(&self) -> bool {
+		matches!(
+			self,
+			Token::UriValue(_)
+				| Token::ExtM3U
+				| Token::EndList
+				| Token::TargetDuration
+				| Token::Version
+				| Token::MediaSequence
+				| Token::Key
+				| Token::AllowCache
+				| Token::PlaylistType
+				| Token::IFramesOnly
+				| Token::Inf
+				| Token::ByteRange
+		)
+	}
 }
 
 #[derive(Debug, PartialEq)]
@@ -96,12 +137,78 @@ pub struct MediaPlaylist {
 	pub segments: Vec<MediaSegment>,
 }
 
+impl MediaPlaylist {
+	/// Renders the playlist as `#EXTM3U` text into `writer`.
+	pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		write!(writer, "{self}")
+	}
+
+	/// Returns the lowest `#EXT-X-VERSION` compatible with the tags present.
+	///
+	/// Follows the HLS version rules: non-integer `#EXTINF` durations require
+	/// version 3, `#EXT-X-BYTERANGE`/`#EXT-X-I-FRAMES-ONLY` require version 4,
+	/// and an `#EXT-X-KEY` with `METHOD=SAMPLE-AES` requires version 5.
+	pub fn required_version(&self) -> u8 {
+		let mut version = 1;
+		if self.segments.iter().any(|s| s.duration.fract() != 0.0) {
+			version = version.max(3);
+		}
+		if self.iframes_only || self.segments.iter().any(|s| s.byte_range.is_some()) {
+			version = version.max(4);
+		}
+		if matches!(self.key, Some(Key { method: Method::SampleAes, .. })) {
+			version = version.max(5);
+		}
+		version
+	}
+}
+
+impl fmt::Display for MediaPlaylist {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "#EXTM3U")?;
+		writeln!(f, "#EXT-X-VERSION:{}", self.version)?;
+		writeln!(f, "#EXT-X-TARGETDURATION:{}", self.target_duration)?;
+		writeln!(f, "#EXT-X-MEDIA-SEQUENCE:{}", self.media_sequence)?;
+		if self.allow_cache {
+			writeln!(f, "#EXT-X-ALLOW-CACHE:YES")?;
+		}
+		writeln!(f, "#EXT-X-PLAYLIST-TYPE:{}", self.playlist_type)?;
+		if self.iframes_only {
+			writeln!(f, "#EXT-X-I-FRAMES-ONLY")?;
+		}
+		if let Some(key) = &self.key {
+			writeln!(f, "{key}")?;
+		}
+		for segment in &self.segments {
+			writeln!(f, "#EXTINF:{:.3},", segment.duration)?;
+			if let Some(range) = &segment.byte_range {
+				writeln!(f, "#EXT-X-BYTERANGE:{range}")?;
+			}
+			writeln!(f, "{}", segment.url)?;
+		}
+		write!(f, "#EXT-X-ENDLIST")
+	}
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Key {
 	pub method: Method,
 	pub uri: String,
 }
 
+impl Key {
+	/// Renders the `#EXT-X-KEY` tag into `writer`.
+	pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		write!(writer, "{self}")
+	}
+}
+
+impl fmt::Display for Key {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#EXT-X-KEY:METHOD={},URI=\"{}\"", self.method, self.uri)
+	}
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Method {
 	Aes128,
@@ -109,21 +216,74 @@ pub enum Method {
 	None,
 }
 
-#[derive(Debug, PartialEq)]
+impl Method {
+	/// Parses an `#EXT-X-KEY` `METHOD` keyword into its [`Method`].
+	fn from_keyword(keyword: &str) -> Result<Self> {
+		match keyword {
+			"AES-128" => Ok(Method::Aes128),
+			"SAMPLE-AES" => Ok(Method::SampleAes),
+			"NONE" => Ok(Method::None),
+			other => bail!("Invalid key method: {other}"),
+		}
+	}
+}
+
+impl fmt::Display for Method {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let keyword = match self {
+			Method::Aes128 => "AES-128",
+			Method::SampleAes => "SAMPLE-AES",
+			Method::None => "NONE",
+		};
+		f.write_str(keyword)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum PlaylistType {
 	Vod,
 	Event,
 }
 
+impl fmt::Display for PlaylistType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			PlaylistType::Vod => "VOD",
+			PlaylistType::Event => "EVENT",
+		})
+	}
+}
+
+/// A sub-range of a resource addressed by an `#EXT-X-BYTERANGE` tag.
+#[derive(Debug, PartialEq)]
+pub struct ByteRange {
+	/// The length of the sub-range in bytes.
+	pub length: usize,
+	/// The absolute start offset in bytes. `None` in the parsed tag means it
+	/// continues immediately after the previous sub-range of the same resource;
+	/// once a segment is built the offset is always resolved.
+	pub offset: Option<usize>,
+}
+
+impl fmt::Display for ByteRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.offset {
+			Some(offset) => write!(f, "{}@{offset}", self.length),
+			None => write!(f, "{}", self.length),
+		}
+	}
+}
+
 #[derive(Debug, PartialEq)]
 pub struct MediaSegment {
 	pub duration: f32,
-	pub byte_range: Option<Range<usize>>,
+	pub byte_range: Option<ByteRange>,
 	pub url: String,
 }
 
 pub fn parse(input: &str) -> Result<MediaPlaylist> {
 	let mut lexer = Token::lexer(input);
+	let mut offsets: HashMap<String, usize> = HashMap::new();
 	let mut playlist = MediaPlaylist {
 		version: 0,
 		media_sequence: 0,
@@ -141,43 +301,46 @@ pub fn parse(input: &str) -> Result<MediaPlaylist> {
 			Token::Version => {
 				playlist.version = match lexer.nth(1).context("Invalid version")?? {
 					Token::Integer(version) => version as u8,
-					_ => bail!("Invalid version"),
+					_ => bail_at!(lexer, "Invalid version"),
 				};
 			}
 			Token::MediaSequence => {
 				playlist.media_sequence = match lexer.nth(1).context("Invalid media sequence")?? {
 					Token::Integer(sequence) => sequence as u32,
-					_ => bail!("Invalid media sequence"),
+					_ => bail_at!(lexer, "Invalid media sequence"),
 				};
 			}
 			Token::Key => {
-				let method = match lexer.nth(3).context("Invalid method")?? {
-					Token::MethodValue(method) => method,
-					_ => bail!("Invalid method"),
-				};
-				let uri = match lexer.nth(3).context("Invalid URI")?? {
-					Token::String(uri) => uri.to_string(),
-					_ => bail!("Invalid key URL"),
-				};
+				let attributes = read_attributes(&mut lexer)?;
+				let method = attributes
+					.get("METHOD")
+					.context("missing key method")?
+					.keyword()
+					.and_then(Method::from_keyword)?;
+				let uri = attributes
+					.get("URI")
+					.context("missing key URI")?
+					.quoted()?
+					.to_string();
 				playlist.key = Some(Key { method, uri });
 			}
 			Token::AllowCache => {
 				playlist.allow_cache = match lexer.nth(1).context("Invalid allow cache")?? {
 					Token::AllowCacheValue(allow_cache) => allow_cache,
-					_ => bail!("Invalid allow cache"),
+					_ => bail_at!(lexer, "Invalid allow cache"),
 				};
 			}
 			Token::TargetDuration => {
 				playlist.target_duration =
 					match lexer.nth(1).context("Invalid target duration")?? {
 						Token::Integer(duration) => duration as u32,
-						_ => bail!("Invalid target duration"),
+						_ => bail_at!(lexer, "Invalid target duration"),
 					};
 			}
 			Token::PlaylistType => {
 				playlist.playlist_type = match lexer.nth(1).context("Invalid playlist type")?? {
 					Token::PlaylistTypeValue(playlist_type) => playlist_type,
-					_ => bail!("Invalid playlist type"),
+					_ => bail_at!(lexer, "Invalid playlist type"),
 				};
 			}
 			Token::IFramesOnly => {
@@ -186,33 +349,35 @@ pub fn parse(input: &str) -> Result<MediaPlaylist> {
 			Token::Inf => {
 				let duration = match lexer.nth(1).context("Invalid duration")?? {
 					Token::Float(duration) => duration as f32,
-					_ => bail!("Invalid duration"),
+					_ => bail_at!(lexer, "Invalid duration"),
 				};
 
-				// let byte_range = match lexer.find(|token| matches!(token, Ok(Token::ByteRange))) {
-				// 	Some(Ok(Token::ByteRange)) => {
-				// 		lexer.next(); // Consume ByteRange token
-				// 		match lexer.next().context("Invalid byte range value")?? {
-				// 			Token::ByteRangeValue(range) => Some(range),
-				// 			_ => None,
-				// 		}
-				// 	}
-				// 	_ => None,
-				// };
-
-				let byte_range = playlist
-					.iframes_only
-					.then(|| match lexer.nth(3) {
-						Some(Ok(Token::ByteRangeValue(range))) => Some(range),
-						_ => None,
-					})
-					.flatten();
-
-				let url_advance = if byte_range.is_some() { 0 } else { 1 };
-				let url = match lexer.nth(url_advance).context("Invalid URL")?? {
-					Token::UriValue(uri) => uri.to_string(),
-					_ => bail!("Invalid URL"),
+				// An optional `#EXT-X-BYTERANGE` may sit between the `#EXTINF`
+				// line and the segment URI, in either order relative to the
+				// trailing comma.
+				let mut byte_range = None;
+				let url = loop {
+					match lexer.next().context("Invalid URL")?? {
+						Token::Comma => (),
+						Token::ByteRange => {
+							byte_range = Some(parse_byte_range(&mut lexer)?);
+						}
+						Token::UriValue(uri) => break uri.to_string(),
+						_ => bail_at!(lexer, "Invalid URL"),
+					}
 				};
+
+				// Resolve an implicit offset against the end of the previous
+				// sub-range of the same resource.
+				let byte_range = byte_range.map(|range: ByteRange| {
+					let offset = range.offset.unwrap_or_else(|| offsets.get(&url).copied().unwrap_or(0));
+					offsets.insert(url.clone(), offset + range.length);
+					ByteRange {
+						length: range.length,
+						offset: Some(offset),
+					}
+				});
+
 				playlist.segments.push(MediaSegment {
 					duration,
 					byte_range,
@@ -224,12 +389,44 @@ pub fn parse(input: &str) -> Result<MediaPlaylist> {
 		}
 	}
 
+	let required = playlist.required_version();
+	if playlist.version != 0 && playlist.version < required {
+		bail_at!(
+			lexer,
+			"declared #EXT-X-VERSION {} is lower than the required version {required}",
+			playlist.version
+		);
+	}
+
 	Ok(playlist)
 }
 
+/// Reads an `#EXT-X-BYTERANGE` value of the form `length[@offset]`, starting at
+/// the `:` that follows the tag.
+fn parse_byte_range<'a>(lexer: &mut logos::Lexer<'a, Token<'a>>) -> Result<ByteRange> {
+	let length = match lexer.nth(1).context("Invalid byte range length")?? {
+		Token::Integer(length) => length,
+		_ => bail_at!(lexer, "Invalid byte range length"),
+	};
+
+	let mut ahead = lexer.clone();
+	let offset = if let Some(Ok(Token::At)) = ahead.next() {
+		lexer.next(); // consume the `@`
+		match lexer.next().context("Invalid byte range offset")?? {
+			Token::Integer(offset) => Some(offset),
+			_ => bail_at!(lexer, "Invalid byte range offset"),
+		}
+	} else {
+		None
+	};
+
+	Ok(ByteRange { length, offset })
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::error::Error;
 
 	#[test]
 	fn test_media_playlist() {
@@ -278,7 +475,55 @@ mod tests {
 		)
 	}
 
-	#[allow(clippy::reversed_empty_ranges)]
+	#[test]
+	fn test_media_playlist_round_trip() {
+		let input = r#"
+			#EXTM3U
+			#EXT-X-TARGETDURATION:17
+			#EXT-X-ALLOW-CACHE:YES
+			#EXT-X-PLAYLIST-TYPE:VOD
+			#EXT-X-KEY:METHOD=AES-128,URI="https://example.com/mon.key"
+			#EXT-X-VERSION:3
+			#EXT-X-MEDIA-SEQUENCE:1
+			#EXTINF:6.006,
+			https://example.com/segment-1.ts
+			#EXTINF:4.588,
+			https://example.com/segment-2.ts
+			#EXT-X-ENDLIST
+		"#;
+
+		let playlist = parse(input).unwrap();
+		let rendered = playlist.to_string();
+		assert_eq!(parse(&rendered).unwrap(), playlist);
+	}
+
+	#[test]
+	fn test_parse_error_carries_span_and_line() {
+		let input = "#EXTM3U\n#EXT-X-VERSION:OOPS\n";
+		match parse(input) {
+			Err(Error::Parse { line, span, .. }) => {
+				assert_eq!(line, 2);
+				assert!(span.start < span.end);
+			}
+			other => panic!("expected a parse error, got {other:?}"),
+		}
+	}
+
+	#[test]
+	fn test_media_playlist_version_too_low() {
+		let input = r#"
+			#EXTM3U
+			#EXT-X-VERSION:3
+			#EXT-X-TARGETDURATION:10
+			#EXT-X-KEY:METHOD=SAMPLE-AES,URI="https://example.com/mon.key"
+			#EXTINF:6.000,
+			https://example.com/segment-1.ts
+			#EXT-X-ENDLIST
+		"#;
+
+		assert!(parse(input).is_err());
+	}
+
 	#[test]
 	fn test_media_playlist_iframes() {
 		let input = r#"
@@ -311,16 +556,55 @@ mod tests {
 				segments: vec![
 					MediaSegment {
 						duration: 1.12,
-						byte_range: Some(1316..376),
+						byte_range: Some(ByteRange {
+							length: 1316,
+							offset: Some(376),
+						}),
 						url: "https://example.com/segment-1.ts".to_string(),
 					},
 					MediaSegment {
 						duration: 6.72,
-						byte_range: Some(44744..7896),
+						byte_range: Some(ByteRange {
+							length: 44744,
+							offset: Some(7896),
+						}),
 						url: "https://example.com/segment-2.ts".to_string(),
 					},
 				],
 			}
 		)
 	}
+
+	#[test]
+	fn test_byte_range_implicit_offset() {
+		let input = r#"
+			#EXTM3U
+			#EXT-X-VERSION:4
+			#EXT-X-TARGETDURATION:10
+			#EXTINF:2.000,
+			#EXT-X-BYTERANGE:1000@0
+			https://example.com/video.ts
+			#EXTINF:2.000,
+			#EXT-X-BYTERANGE:1000
+			https://example.com/video.ts
+			#EXT-X-ENDLIST
+		"#;
+
+		let playlist = parse(input).unwrap();
+		assert_eq!(
+			playlist.segments[0].byte_range,
+			Some(ByteRange {
+				length: 1000,
+				offset: Some(0),
+			})
+		);
+		// The second sub-range of the same resource continues where the first ended.
+		assert_eq!(
+			playlist.segments[1].byte_range,
+			Some(ByteRange {
+				length: 1000,
+				offset: Some(1000),
+			})
+		);
+	}
 }
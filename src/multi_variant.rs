@@ -1,32 +1,33 @@
+use std::fmt;
+use std::io;
+
 use crate::{
-	bail,
-	error::{Context as _, Error, Result},
+	attributes::{read_attributes, AttrValue, AttributeToken, Attributes},
+	bail, bail_at,
+	error::{Context as _, Result},
 };
 use logos::Logos;
 
-#[derive(Logos, Debug, PartialEq)]
-#[logos(skip r"[ \t\n\f]+")]
+#[derive(Logos, Debug, Clone, PartialEq)]
+#[logos(extras = usize)]
 #[logos(error = String)]
 pub enum Token<'a> {
+	#[regex(r"[ \t\n\f]+", |lex| {
+		lex.extras += lex.slice().bytes().filter(|&b| b == b'\n').count();
+		logos::Skip
+	})]
+	Whitespace,
+
 	#[token("#EXTM3U")]
 	ExtM3U,
 	#[token("#EXT-X-STREAM-INF")]
 	StreamInf,
 	#[token("#EXT-X-I-FRAME-STREAM-INF")]
 	IFrameStreamInf,
-
-	#[token("PROGRAM-ID")]
-	ProgramId,
-	#[token("BANDWIDTH")]
-	Bandwidth,
-	#[token("RESOLUTION")]
-	Resolution,
-	#[token("FRAME-RATE")]
-	FrameRate,
-	#[token("CODECS")]
-	Codecs,
-	#[token("URI")]
-	Uri,
+	#[token("#EXT-X-MEDIA")]
+	Media,
+	#[token("#EXT-X-VERSION")]
+	Version,
 
 	#[token("=")]
 	Equal,
@@ -51,10 +52,54 @@ pub enum Token<'a> {
 	ResolutionValue((usize, usize)),
 	#[regex(r"[a-zA-Z0-9\-_]+\.m3u8")]
 	UriValue(&'a str),
+
+	/// An attribute name (or unquoted enumerated keyword) in an attribute list.
+	#[regex(r"[A-Z][A-Z0-9\-]*", |lex| lex.slice(), priority = 1)]
+	AttrName(&'a str),
+}
+
+impl<'a> AttributeToken<'a> for Token<'a> {
+	fn attr_name(&self) -> Option<&'a str> {
+		match self {
+			Token::AttrName(name) => Some(name),
+			_ => None,
+		}
+	}
+
+	fn attr_value(&self) -> Option<AttrValue<'a>> {
+		match self {
+			Token::String(value) => Some(AttrValue::Quoted(value)),
+			Token::Integer(value) => Some(AttrValue::Integer(*value)),
+			Token::Float(value) => Some(AttrValue::Float(*value)),
+			Token::ResolutionValue((width, height)) => Some(AttrValue::Resolution(*width, *height)),
+			Token::AttrName(value) => Some(AttrValue::Keyword(value)),
+			_ => None,
+		}
+	}
+
+	fn is_equal(&self) -> bool {
+		matches!(self, Token::Equal)
+	}
+
+	fn is_separator(&self) -> bool {
+		matches!(self, Token::Comma | Token::Colon)
+	}
+
+	fn ends_line(&self) -> bool {
+		matches!(
+			self,
+			Token::UriValue(_)
+				| Token::ExtM3U | Token::StreamInf
+				| Token::IFrameStreamInf
+				| Token::Media | Token::Version
+		)
+	}
 }
 
 #[derive(Debug, PartialEq)]
 pub struct MultiVariantPlaylist {
+	/// The declared `#EXT-X-VERSION`, or `0` when the playlist omits the tag.
+	pub version: u8,
 	/// These lines define the variant streams.
 	/// Each line represents a different version of the same content, encoded at different bitrates and resolutions.
 	/// This allows the player to dynamically switch between streams based on the user's network conditions, a feature known as Adaptive Bitrate Streaming (ABR)
@@ -63,24 +108,397 @@ pub struct MultiVariantPlaylist {
 	/// I-frames are keyframes in the video that contain the complete image information.
 	/// These streams allow for faster seeking and trick play.
 	pub frame_streams: Vec<FrameStream>,
+	/// Alternative renditions (audio, video, subtitles, closed captions) that
+	/// variant streams reference by group id.
+	pub media: Vec<Media>,
+}
+
+/// The kind of alternative rendition an `#EXT-X-MEDIA` tag describes.
+#[derive(Debug, PartialEq)]
+pub enum MediaType {
+	Audio,
+	Video,
+	Subtitles,
+	ClosedCaptions,
+}
+
+impl MediaType {
+	/// Parses an `#EXT-X-MEDIA` `TYPE` keyword into its [`MediaType`].
+	fn from_keyword(keyword: &str) -> Result<Self> {
+		match keyword {
+			"AUDIO" => Ok(MediaType::Audio),
+			"VIDEO" => Ok(MediaType::Video),
+			"SUBTITLES" => Ok(MediaType::Subtitles),
+			"CLOSED-CAPTIONS" => Ok(MediaType::ClosedCaptions),
+			other => bail!("Invalid media type: {other}"),
+		}
+	}
+}
+
+impl fmt::Display for MediaType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			MediaType::Audio => "AUDIO",
+			MediaType::Video => "VIDEO",
+			MediaType::Subtitles => "SUBTITLES",
+			MediaType::ClosedCaptions => "CLOSED-CAPTIONS",
+		})
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Media {
+	/// The kind of rendition (audio, video, subtitles, closed captions).
+	pub r#type: MediaType,
+	/// The group this rendition belongs to; variant streams reference it by this id.
+	pub group_id: String,
+	/// A human-readable name for the rendition.
+	pub name: String,
+	/// The primary language of the rendition, as an RFC 5646 tag.
+	pub language: Option<String>,
+	/// Whether the client should play this rendition absent other information.
+	pub default: bool,
+	/// Whether the client may auto-select this rendition when it matches user preferences.
+	pub autoselect: bool,
+	/// The URI of the media playlist for this rendition, if it is rendered separately.
+	pub uri: Option<String>,
+	/// The audio channel count / configuration (the `CHANNELS` attribute).
+	pub channels: Option<String>,
+}
+
+impl Media {
+	/// Renders the `#EXT-X-MEDIA` tag into `writer`.
+	pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		write!(writer, "{self}")
+	}
+
+	/// Builds a rendition from a parsed `#EXT-X-MEDIA` attribute list.
+	fn from_attributes(attributes: &Attributes) -> Result<Self> {
+		Ok(Media {
+			r#type: attributes
+				.get("TYPE")
+				.context("missing media type")?
+				.keyword()
+				.and_then(MediaType::from_keyword)?,
+			group_id: attributes
+				.get("GROUP-ID")
+				.context("missing media group id")?
+				.quoted()?
+				.to_string(),
+			name: attributes
+				.get("NAME")
+				.context("missing media name")?
+				.quoted()?
+				.to_string(),
+			language: attributes
+				.get("LANGUAGE")
+				.map(AttrValue::quoted)
+				.transpose()?
+				.map(str::to_string),
+			default: flag(attributes.get("DEFAULT"))?,
+			autoselect: flag(attributes.get("AUTOSELECT"))?,
+			uri: attributes
+				.get("URI")
+				.map(AttrValue::quoted)
+				.transpose()?
+				.map(str::to_string),
+			channels: attributes
+				.get("CHANNELS")
+				.map(AttrValue::quoted)
+				.transpose()?
+				.map(str::to_string),
+		})
+	}
+}
+
+impl fmt::Display for Media {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"#EXT-X-MEDIA:TYPE={},GROUP-ID=\"{}\",NAME=\"{}\"",
+			self.r#type, self.group_id, self.name
+		)?;
+		if let Some(language) = &self.language {
+			write!(f, ",LANGUAGE=\"{language}\"")?;
+		}
+		if self.default {
+			write!(f, ",DEFAULT=YES")?;
+		}
+		if self.autoselect {
+			write!(f, ",AUTOSELECT=YES")?;
+		}
+		if let Some(uri) = &self.uri {
+			write!(f, ",URI=\"{uri}\"")?;
+		}
+		if let Some(channels) = &self.channels {
+			write!(f, ",CHANNELS=\"{channels}\"")?;
+		}
+		Ok(())
+	}
+}
+
+/// Reads a `YES`/`NO` boolean attribute, defaulting to `false` when absent.
+fn flag(value: Option<&AttrValue>) -> Result<bool> {
+	match value {
+		Some(value) => match value.keyword()? {
+			"YES" => Ok(true),
+			"NO" => Ok(false),
+			other => bail!("expected YES or NO, found {other}"),
+		},
+		None => Ok(false),
+	}
+}
+
+/// The High-bandwidth Digital Content Protection level a variant requires.
+#[derive(Debug, PartialEq)]
+pub enum HdcpLevel {
+	None,
+	Type0,
+	Type1,
+}
+
+impl HdcpLevel {
+	/// Parses an `HDCP-LEVEL` keyword into its [`HdcpLevel`].
+	fn from_keyword(keyword: &str) -> Result<Self> {
+		match keyword {
+			"NONE" => Ok(HdcpLevel::None),
+			"TYPE-0" => Ok(HdcpLevel::Type0),
+			"TYPE-1" => Ok(HdcpLevel::Type1),
+			other => bail!("Invalid HDCP level: {other}"),
+		}
+	}
+}
+
+impl fmt::Display for HdcpLevel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			HdcpLevel::None => "NONE",
+			HdcpLevel::Type0 => "TYPE-0",
+			HdcpLevel::Type1 => "TYPE-1",
+		})
+	}
+}
+
+/// The dynamic range of the video in a variant stream.
+#[derive(Debug, PartialEq)]
+pub enum VideoRange {
+	Sdr,
+	Pq,
+	Hlg,
+}
+
+impl VideoRange {
+	/// Parses a `VIDEO-RANGE` keyword into its [`VideoRange`].
+	fn from_keyword(keyword: &str) -> Result<Self> {
+		match keyword {
+			"SDR" => Ok(VideoRange::Sdr),
+			"PQ" => Ok(VideoRange::Pq),
+			"HLG" => Ok(VideoRange::Hlg),
+			other => bail!("Invalid video range: {other}"),
+		}
+	}
+}
+
+impl fmt::Display for VideoRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			VideoRange::Sdr => "SDR",
+			VideoRange::Pq => "PQ",
+			VideoRange::Hlg => "HLG",
+		})
+	}
 }
 
 #[derive(Debug, PartialEq)]
 pub struct VariantStream {
 	/// Identifies the program or content.
 	pub program_id: Option<u8>,
-	/// The average bitrate of the stream in bits per second.
+	/// The peak bitrate of the stream in bits per second.
 	pub bandwidth: u32,
+	/// The average bitrate of the stream in bits per second.
+	pub average_bandwidth: Option<u32>,
 	/// The resolution of the video (e.g., 1440x1080).
 	pub resolution: (u16, u16),
 	/// The frame rate of the video.
 	pub frame_rate: Option<f32>,
-	/// Specifies the codecs used for the audio and video streams.
-	pub codecs: Option<String>,
+	/// The HDCP level the content requires, if declared.
+	pub hdcp_level: Option<HdcpLevel>,
+	/// The dynamic range of the video, if declared.
+	pub video_range: Option<VideoRange>,
+	/// The codecs used by the streams, one entry per comma-separated codec.
+	pub codecs: Vec<String>,
+	/// The `GROUP-ID` of the audio rendition group this variant uses, if any.
+	pub audio: Option<String>,
+	/// The `GROUP-ID` of the video rendition group this variant uses, if any.
+	pub video: Option<String>,
+	/// The `GROUP-ID` of the subtitle rendition group this variant uses, if any.
+	pub subtitles: Option<String>,
+	/// The `GROUP-ID` of the closed-caption rendition group this variant uses, if any.
+	pub closed_captions: Option<String>,
 	/// The URI of the m3u8 file containing the media segments for this variant.
 	pub uri: String,
 }
 
+impl MultiVariantPlaylist {
+	/// Renders the playlist as `#EXTM3U` text into `writer`.
+	pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		write!(writer, "{self}")
+	}
+
+	/// Returns the lowest `#EXT-X-VERSION` compatible with the tags present.
+	///
+	/// Follows the HLS version rules: a `VIDEO-RANGE` or `HDCP-LEVEL` attribute
+	/// on any variant stream requires version 7.
+	pub fn required_version(&self) -> u8 {
+		let mut version = 1;
+		if self
+			.variant_streams
+			.iter()
+			.any(|v| v.video_range.is_some() || v.hdcp_level.is_some())
+		{
+			version = version.max(7);
+		}
+		version
+	}
+}
+
+impl fmt::Display for MultiVariantPlaylist {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "#EXTM3U")?;
+		if self.version != 0 {
+			writeln!(f, "#EXT-X-VERSION:{}", self.version)?;
+		}
+		for rendition in &self.media {
+			writeln!(f, "{rendition}")?;
+		}
+		for variant in &self.variant_streams {
+			writeln!(f, "{variant}")?;
+		}
+		for frame in &self.frame_streams {
+			writeln!(f, "{frame}")?;
+		}
+		Ok(())
+	}
+}
+
+impl VariantStream {
+	/// Renders the `#EXT-X-STREAM-INF` tag and its URI line into `writer`.
+	pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		write!(writer, "{self}")
+	}
+
+	/// Builds a variant stream from a parsed `#EXT-X-STREAM-INF` attribute list
+	/// and the URI that follows it on the next line.
+	fn from_attributes(attributes: &Attributes, uri: String) -> Result<Self> {
+		Ok(VariantStream {
+			program_id: attributes
+				.get("PROGRAM-ID")
+				.map(AttrValue::integer)
+				.transpose()?
+				.map(|value| value as u8),
+			bandwidth: attributes
+				.get("BANDWIDTH")
+				.context("missing required BANDWIDTH attribute")?
+				.integer()? as u32,
+			average_bandwidth: attributes
+				.get("AVERAGE-BANDWIDTH")
+				.map(AttrValue::integer)
+				.transpose()?
+				.map(|value| value as u32),
+			resolution: attributes
+				.get("RESOLUTION")
+				.map(AttrValue::resolution)
+				.transpose()?
+				.map(|(width, height)| (width as u16, height as u16))
+				.unwrap_or((0, 0)),
+			frame_rate: attributes
+				.get("FRAME-RATE")
+				.map(AttrValue::float)
+				.transpose()?
+				.map(|rate| rate as f32),
+			hdcp_level: attributes
+				.get("HDCP-LEVEL")
+				.map(|value| value.keyword().and_then(HdcpLevel::from_keyword))
+				.transpose()?,
+			video_range: attributes
+				.get("VIDEO-RANGE")
+				.map(|value| value.keyword().and_then(VideoRange::from_keyword))
+				.transpose()?,
+			codecs: attributes
+				.get("CODECS")
+				.map(AttrValue::quoted)
+				.transpose()?
+				.map(|codecs| codecs.split(',').map(str::to_string).collect())
+				.unwrap_or_default(),
+			audio: attributes
+				.get("AUDIO")
+				.map(AttrValue::text)
+				.transpose()?
+				.map(str::to_string),
+			video: attributes
+				.get("VIDEO")
+				.map(AttrValue::text)
+				.transpose()?
+				.map(str::to_string),
+			subtitles: attributes
+				.get("SUBTITLES")
+				.map(AttrValue::text)
+				.transpose()?
+				.map(str::to_string),
+			closed_captions: attributes
+				.get("CLOSED-CAPTIONS")
+				.map(AttrValue::text)
+				.transpose()?
+				.map(str::to_string),
+			uri,
+		})
+	}
+}
+
+impl fmt::Display for VariantStream {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#EXT-X-STREAM-INF:")?;
+		let mut attributes = Vec::new();
+		if let Some(program_id) = self.program_id {
+			attributes.push(format!("PROGRAM-ID={program_id}"));
+		}
+		attributes.push(format!("BANDWIDTH={}", self.bandwidth));
+		if let Some(average_bandwidth) = self.average_bandwidth {
+			attributes.push(format!("AVERAGE-BANDWIDTH={average_bandwidth}"));
+		}
+		attributes.push(format!(
+			"RESOLUTION={}x{}",
+			self.resolution.0, self.resolution.1
+		));
+		if let Some(frame_rate) = self.frame_rate {
+			attributes.push(format!("FRAME-RATE={frame_rate:.3}"));
+		}
+		if let Some(hdcp_level) = &self.hdcp_level {
+			attributes.push(format!("HDCP-LEVEL={hdcp_level}"));
+		}
+		if let Some(video_range) = &self.video_range {
+			attributes.push(format!("VIDEO-RANGE={video_range}"));
+		}
+		if !self.codecs.is_empty() {
+			attributes.push(format!("CODECS=\"{}\"", self.codecs.join(",")));
+		}
+		if let Some(audio) = &self.audio {
+			attributes.push(format!("AUDIO=\"{audio}\""));
+		}
+		if let Some(video) = &self.video {
+			attributes.push(format!("VIDEO=\"{video}\""));
+		}
+		if let Some(subtitles) = &self.subtitles {
+			attributes.push(format!("SUBTITLES=\"{subtitles}\""));
+		}
+		if let Some(closed_captions) = &self.closed_captions {
+			attributes.push(format!("CLOSED-CAPTIONS=\"{closed_captions}\""));
+		}
+		write!(f, "{}", attributes.join(","))?;
+		write!(f, "\n{}", self.uri)
+	}
+}
+
 #[derive(Debug, PartialEq)]
 pub struct FrameStream {
 	/// The average bitrate of the I-frame stream.
@@ -93,137 +511,114 @@ pub struct FrameStream {
 	pub uri: String,
 }
 
+impl FrameStream {
+	/// Renders the `#EXT-X-I-FRAME-STREAM-INF` tag into `writer`.
+	pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		write!(writer, "{self}")
+	}
+
+	/// Builds an I-frame stream from a parsed `#EXT-X-I-FRAME-STREAM-INF`
+	/// attribute list, whose URI is carried inline as a quoted `URI` attribute.
+	fn from_attributes(attributes: &Attributes) -> Result<Self> {
+		Ok(FrameStream {
+			bandwidth: attributes
+				.get("BANDWIDTH")
+				.map(AttrValue::integer)
+				.transpose()?
+				.unwrap_or(0) as u32,
+			resolution: attributes
+				.get("RESOLUTION")
+				.map(AttrValue::resolution)
+				.transpose()?
+				.map(|(width, height)| (width as u16, height as u16))
+				.unwrap_or((0, 0)),
+			codecs: attributes
+				.get("CODECS")
+				.map(AttrValue::quoted)
+				.transpose()?
+				.unwrap_or_default()
+				.to_string(),
+			uri: attributes
+				.get("URI")
+				.map(AttrValue::quoted)
+				.transpose()?
+				.context("missing I-frame stream URI")?
+				.to_string(),
+		})
+	}
+}
+
+impl fmt::Display for FrameStream {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH={},RESOLUTION={}x{},CODECS=\"{}\",URI=\"{}\"",
+			self.bandwidth, self.resolution.0, self.resolution.1, self.codecs, self.uri
+		)
+	}
+}
+
 pub fn parse(input: &str) -> Result<MultiVariantPlaylist> {
 	let mut lexer = Token::lexer(input);
+	let mut version = 0;
 	let mut variant_streams = Vec::new();
 	let mut frame_streams = Vec::new();
+	let mut media = Vec::new();
 
 	while let Some(token) = lexer.next() {
 		match token? {
 			Token::ExtM3U => (),
+			Token::Version => {
+				version = match lexer.nth(1).context("Invalid version")?? {
+					Token::Integer(value) => value as u8,
+					_ => bail_at!(lexer, "Invalid version"),
+				};
+			}
 			Token::StreamInf => {
 				variant_streams.push(parse_variant_stream(&mut lexer)?);
 			}
 			Token::IFrameStreamInf => {
 				frame_streams.push(parse_frame_stream(&mut lexer)?);
 			}
+			Token::Media => {
+				media.push(Media::from_attributes(&read_attributes(&mut lexer)?)?);
+			}
 			_ => (),
 		}
 	}
 
-	Ok(MultiVariantPlaylist {
+	let playlist = MultiVariantPlaylist {
+		version,
 		variant_streams,
 		frame_streams,
-	})
-}
+		media,
+	};
 
-fn parse_variant_stream<'a>(lexer: &mut logos::Lexer<'a, Token<'a>>) -> Result<VariantStream> {
-	let mut program_id = None;
-	let mut bandwidth = 0;
-	let mut resolution = (0, 0);
-	let mut frame_rate = None;
-	let mut codecs = None;
-	let mut uri = String::new();
-
-	while let Some(token) = lexer.next() {
-		match token? {
-			Token::Colon => (),
-			Token::Equal => (),
-			Token::Comma => (),
-			Token::ProgramId => {
-				program_id = match lexer.nth(1).context("program id")?? {
-					Token::Integer(value) => Some(value as u8),
-					_ => bail!("Invalid program id"),
-				};
-			}
-			Token::Bandwidth => {
-				bandwidth = match lexer.nth(1).context("bandwidth")?? {
-					Token::Integer(value) => value as u32,
-					_ => bail!("Invalid bandwidth"),
-				};
-			}
-			Token::Resolution => {
-				resolution = match lexer.nth(1).context("resolution")?? {
-					Token::ResolutionValue(res) => (res.0 as u16, res.1 as u16),
-					_ => bail!("Invalid resolution"),
-				};
-			}
-			Token::FrameRate => {
-				frame_rate = match lexer.nth(1).context("frame rate")?? {
-					Token::Float(rate) => Some(rate as f32),
-					_ => bail!("Invalid frame rate"),
-				};
-			}
-			Token::Codecs => {
-				codecs = Some(match lexer.nth(1).context("codecs")?? {
-					Token::String(codec) => codec.to_string(),
-					_ => bail!("Invalid codecs"),
-				});
-			}
-			Token::UriValue(value) => {
-				uri = value.to_string();
-				break;
-			}
-			_ => bail!("Invalid variant stream"),
-		}
+	let required = playlist.required_version();
+	if playlist.version != 0 && playlist.version < required {
+		bail_at!(
+			lexer,
+			"declared #EXT-X-VERSION {} is lower than the required version {required}",
+			playlist.version
+		);
 	}
 
-	Ok(VariantStream {
-		program_id,
-		bandwidth,
-		resolution,
-		frame_rate,
-		codecs,
-		uri,
-	})
+	Ok(playlist)
 }
 
-fn parse_frame_stream<'a>(lexer: &mut logos::Lexer<'a, Token<'a>>) -> Result<FrameStream> {
-	let mut bandwidth = 0;
-	let mut resolution = (0, 0);
-	let mut codecs = String::new();
-	let mut uri = String::new();
+fn parse_variant_stream<'a>(lexer: &mut logos::Lexer<'a, Token<'a>>) -> Result<VariantStream> {
+	let attributes = read_attributes(lexer)?;
+	let uri = match lexer.next().context("expected a variant stream URI")?? {
+		Token::UriValue(value) => value.to_string(),
+		_ => bail_at!(lexer, "Invalid variant stream URI"),
+	};
 
-	while let Some(token) = lexer.next() {
-		match token? {
-			Token::Colon => (),
-			Token::Equal => (),
-			Token::Comma => (),
-			Token::Bandwidth => {
-				bandwidth = match lexer.nth(1).context("bandwidth")?? {
-					Token::Integer(value) => value as u32,
-					_ => bail!("Invalid bandwidth"),
-				};
-			}
-			Token::Resolution => {
-				resolution = match lexer.nth(1).context("resolution")?? {
-					Token::ResolutionValue(res) => (res.0 as u16, res.1 as u16),
-					_ => bail!("Invalid resolution"),
-				};
-			}
-			Token::Codecs => {
-				codecs = match lexer.nth(1).context("codecs")?? {
-					Token::String(codec) => codec.to_string(),
-					_ => bail!("Invalid codecs"),
-				};
-			}
-			Token::Uri => {
-				uri = match lexer.nth(1).context("uri")?? {
-					Token::String(uri) => uri.to_string(),
-					_ => bail!("Invalid uri"),
-				};
-				break;
-			}
-			_ => bail!("Invalid frame stream"),
-		}
-	}
+	VariantStream::from_attributes(&attributes, uri)
+}
 
-	Ok(FrameStream {
-		bandwidth,
-		resolution,
-		codecs,
-		uri,
-	})
+fn parse_frame_stream<'a>(lexer: &mut logos::Lexer<'a, Token<'a>>) -> Result<FrameStream> {
+	let attributes = read_attributes(lexer)?;
+	FrameStream::from_attributes(&attributes)
 }
 
 #[test]
@@ -246,29 +641,51 @@ fn test_variant_stream_token() {
 	assert_eq!(
 		multi_variant_playlist,
 		MultiVariantPlaylist {
+			version: 0,
 			variant_streams: vec![
 				VariantStream {
 					program_id: Some(1),
 					bandwidth: 2553505,
+					average_bandwidth: None,
 					resolution: (1920, 1080),
 					frame_rate: Some(25.0),
-					codecs: Some("avc1.640032,mp4a.40.2".to_string()),
+					hdcp_level: None,
+					video_range: None,
+					codecs: vec!["avc1.640032".to_string(), "mp4a.40.2".to_string()],
+					audio: None,
+					video: None,
+					subtitles: None,
+					closed_captions: None,
 					uri: "index-f1-v1-a1.m3u8".to_string(),
 				},
 				VariantStream {
 					program_id: Some(1),
 					bandwidth: 1420969,
+					average_bandwidth: None,
 					resolution: (1280, 720),
 					frame_rate: Some(25.0),
-					codecs: Some("avc1.64001f,mp4a.40.2".to_string()),
+					hdcp_level: None,
+					video_range: None,
+					codecs: vec!["avc1.64001f".to_string(), "mp4a.40.2".to_string()],
+					audio: None,
+					video: None,
+					subtitles: None,
+					closed_captions: None,
 					uri: "index-f2-v1-a1.m3u8".to_string(),
 				},
 				VariantStream {
 					program_id: Some(1),
 					bandwidth: 641061,
+					average_bandwidth: None,
 					resolution: (640, 360),
 					frame_rate: Some(25.0),
-					codecs: Some("avc1.64001e,mp4a.40.2".to_string()),
+					hdcp_level: None,
+					video_range: None,
+					codecs: vec!["avc1.64001e".to_string(), "mp4a.40.2".to_string()],
+					audio: None,
+					video: None,
+					subtitles: None,
+					closed_captions: None,
 					uri: "index-f3-v1-a1.m3u8".to_string(),
 				},
 			],
@@ -292,6 +709,119 @@ fn test_variant_stream_token() {
 					uri: "iframes-f3-v1-a1.m3u8".to_string(),
 				},
 			],
+			media: Vec::new(),
 		}
 	);
 }
+
+#[test]
+fn test_media_renditions() {
+	let input = "
+		#EXTM3U
+		#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud1\",NAME=\"English\",LANGUAGE=\"en\",DEFAULT=YES,AUTOSELECT=YES,CHANNELS=\"2\",URI=\"audio-en.m3u8\"
+		#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"sub1\",NAME=\"English\",LANGUAGE=\"en\",URI=\"subs-en.m3u8\"
+		#EXT-X-STREAM-INF:BANDWIDTH=2553505,RESOLUTION=1920x1080,CODECS=\"avc1.640032\",AUDIO=\"aud1\",SUBTITLES=\"sub1\",CLOSED-CAPTIONS=NONE
+		index-f1-v1-a1.m3u8
+	";
+
+	let playlist = parse(input).unwrap();
+	assert_eq!(
+		playlist.media,
+		vec![
+			Media {
+				r#type: MediaType::Audio,
+				group_id: "aud1".to_string(),
+				name: "English".to_string(),
+				language: Some("en".to_string()),
+				default: true,
+				autoselect: true,
+				uri: Some("audio-en.m3u8".to_string()),
+				channels: Some("2".to_string()),
+			},
+			Media {
+				r#type: MediaType::Subtitles,
+				group_id: "sub1".to_string(),
+				name: "English".to_string(),
+				language: Some("en".to_string()),
+				default: false,
+				autoselect: false,
+				uri: Some("subs-en.m3u8".to_string()),
+				channels: None,
+			},
+		]
+	);
+
+	let variant = &playlist.variant_streams[0];
+	assert_eq!(variant.audio.as_deref(), Some("aud1"));
+	assert_eq!(variant.subtitles.as_deref(), Some("sub1"));
+	assert_eq!(variant.closed_captions.as_deref(), Some("NONE"));
+}
+
+#[test]
+fn test_variant_stream_typed_attributes() {
+	let input = "
+		#EXTM3U
+		#EXT-X-STREAM-INF:BANDWIDTH=2553505,AVERAGE-BANDWIDTH=2000000,RESOLUTION=1920x1080,VIDEO-RANGE=PQ,HDCP-LEVEL=TYPE-0,CODECS=\"avc1.640032,mp4a.40.2\"
+		index-f1-v1-a1.m3u8
+	";
+
+	let variant = &parse(input).unwrap().variant_streams[0];
+	assert_eq!(variant.average_bandwidth, Some(2000000));
+	assert_eq!(variant.video_range, Some(VideoRange::Pq));
+	assert_eq!(variant.hdcp_level, Some(HdcpLevel::Type0));
+	assert_eq!(variant.codecs, vec!["avc1.640032", "mp4a.40.2"]);
+}
+
+#[test]
+fn test_master_playlist_version() {
+	let input = "
+		#EXTM3U
+		#EXT-X-VERSION:7
+		#EXT-X-STREAM-INF:BANDWIDTH=2553505,RESOLUTION=1920x1080,VIDEO-RANGE=PQ,CODECS=\"avc1.640032\"
+		index-f1-v1-a1.m3u8
+	";
+
+	let playlist = parse(input).unwrap();
+	assert_eq!(playlist.version, 7);
+	assert_eq!(playlist.required_version(), 7);
+}
+
+#[test]
+fn test_master_playlist_version_too_low() {
+	let input = "
+		#EXTM3U
+		#EXT-X-VERSION:4
+		#EXT-X-STREAM-INF:BANDWIDTH=2553505,RESOLUTION=1920x1080,VIDEO-RANGE=PQ,CODECS=\"avc1.640032\"
+		index-f1-v1-a1.m3u8
+	";
+
+	assert!(parse(input).is_err());
+}
+
+#[test]
+fn test_variant_stream_requires_bandwidth() {
+	let input = "
+		#EXTM3U
+		#EXT-X-STREAM-INF:RESOLUTION=1920x1080,CODECS=\"avc1.640032\"
+		index-f1-v1-a1.m3u8
+	";
+
+	assert!(parse(input).is_err());
+}
+
+#[test]
+fn test_multi_variant_round_trip() {
+	let input = "
+		#EXTM3U
+		#EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=2553505,RESOLUTION=1920x1080,FRAME-RATE=25.000,CODECS=\"avc1.640032,mp4a.40.2\"
+		index-f1-v1-a1.m3u8
+		#EXT-X-STREAM-INF:PROGRAM-ID=1,BANDWIDTH=641061,RESOLUTION=640x360,FRAME-RATE=25.000,CODECS=\"avc1.64001e,mp4a.40.2\"
+		index-f3-v1-a1.m3u8
+
+		#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=217533,RESOLUTION=1920x1080,CODECS=\"avc1.640032\",URI=\"iframes-f1-v1-a1.m3u8\"
+	";
+
+	let playlist = parse(input).unwrap();
+	let rendered = playlist.to_string();
+	assert_eq!(parse(&rendered).unwrap(), playlist);
+}